@@ -0,0 +1,181 @@
+use crate::utilities::BitVectorIter;
+use crate::{BitMatrix, Cell, Fingerprint, Layer, MasterCell, Net, NetId, PinId};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+/// A net's previously rendered routing solution, cached alongside the
+/// fingerprint it was produced from.
+type RenderedSolution = String;
+
+/// Full placement-and-routing state for one design.
+#[derive(Debug)]
+pub struct Chip {
+    pub layers: Vec<Layer>,
+    pub master_cells: Vec<MasterCell>,
+    pub cells: Vec<Cell>,
+    pub nets: Vec<Net>,
+
+    /// Last-seen fingerprint and rendered solution per net, so unchanged
+    /// nets can be skipped on the next solve pass.
+    fingerprint_cache: HashMap<NetId, (Fingerprint, RenderedSolution)>,
+
+    /// One row per cell index; `SameGGrid`/`AdjHGGrid` conflicts are
+    /// recorded as bits instead of `HashSet` adjacency.
+    conflict_adjacency: BitMatrix,
+}
+
+impl Chip {
+    pub fn new(layers: Vec<Layer>, master_cells: Vec<MasterCell>, cells: Vec<Cell>, nets: Vec<Net>) -> Self {
+        Self::try_new(layers, master_cells, cells, nets).expect("failed to build Chip")
+    }
+
+    /// Fallible loader for very large designs: checks the `fingerprint_cache`
+    /// and conflict-adjacency allocations instead of aborting on allocation
+    /// failure, so a server routing many chips can degrade gracefully under
+    /// memory pressure.
+    pub fn try_new(
+        layers: Vec<Layer>,
+        master_cells: Vec<MasterCell>,
+        cells: Vec<Cell>,
+        nets: Vec<Net>,
+    ) -> Result<Self> {
+        let num_cells = cells.len();
+
+        let mut fingerprint_cache = HashMap::new();
+        fingerprint_cache.try_reserve(nets.len())?;
+
+        Ok(Self {
+            layers,
+            master_cells,
+            cells,
+            nets,
+            fingerprint_cache,
+            conflict_adjacency: BitMatrix::try_new(num_cells, num_cells)?,
+        })
+    }
+
+    /// Records a `SameGGrid`/`AdjHGGrid` conflict between two cells.
+    pub fn mark_conflict(&mut self, a: usize, b: usize) {
+        self.conflict_adjacency.set(a, b);
+        self.conflict_adjacency.set(b, a);
+    }
+
+    /// Iterates the cell indices conflicting with `cell`.
+    pub fn conflicts_of(&self, cell: usize) -> BitVectorIter<'_> {
+        self.conflict_adjacency.iter_row(cell)
+    }
+
+    /// Unions `other`'s conflict neighbors into `cell`'s row. Returns
+    /// whether any new neighbor was added.
+    pub fn merge_conflicts(&mut self, cell: usize, other: usize) -> bool {
+        self.conflict_adjacency.union_into(cell, other)
+    }
+
+    /// Returns the ids of nets touching `moved_cells` whose fingerprint has
+    /// changed since the last call (or that haven't been seen before),
+    /// refreshing the cache for each one so the next call only reports
+    /// further changes.
+    pub fn dirty_nets(&mut self, moved_cells: &[Cell]) -> Vec<NetId> {
+        let moved_pins: HashSet<PinId> = moved_cells
+            .iter()
+            .flat_map(|cell| cell.pins.iter().copied())
+            .collect();
+
+        let mut dirty = Vec::new();
+
+        for net in &self.nets {
+            let touches_moved_cell = net
+                .tree
+                .nodes()
+                .iter()
+                .filter_map(|node| node.id)
+                .any(|pin| moved_pins.contains(&pin));
+
+            if !touches_moved_cell {
+                continue;
+            }
+
+            let fingerprint = net.fingerprint();
+            let unchanged = self
+                .fingerprint_cache
+                .get(&net.id)
+                .is_some_and(|(cached, _)| *cached == fingerprint);
+
+            if unchanged {
+                continue;
+            }
+
+            self.fingerprint_cache
+                .insert(net.id, (fingerprint, net.to_string()));
+            dirty.push(net.id);
+        }
+
+        dirty
+    }
+}
+
+#[cfg(feature = "serde")]
+mod json {
+    use super::Chip;
+    use crate::{Cell, Layer, MasterCell, Net};
+    use std::io::{Read, Write};
+
+    /// Plain data shape `Chip` round-trips through; the fingerprint cache
+    /// and conflict adjacency are left out of the checkpoint and start
+    /// empty after a load.
+    #[derive(serde::Serialize)]
+    struct ChipRef<'a> {
+        layers: &'a [Layer],
+        master_cells: &'a [MasterCell],
+        cells: &'a [Cell],
+        nets: &'a [Net],
+    }
+
+    #[derive(serde::Deserialize)]
+    struct ChipOwned {
+        layers: Vec<Layer>,
+        master_cells: Vec<MasterCell>,
+        cells: Vec<Cell>,
+        nets: Vec<Net>,
+    }
+
+    impl serde::Serialize for Chip {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            ChipRef {
+                layers: &self.layers,
+                master_cells: &self.master_cells,
+                cells: &self.cells,
+                nets: &self.nets,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> serde::Deserialize<'de> for Chip {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let owned = ChipOwned::deserialize(deserializer)?;
+            Chip::try_new(owned.layers, owned.master_cells, owned.cells, owned.nets)
+                .map_err(serde::de::Error::custom)
+        }
+    }
+
+    impl Chip {
+        /// Writes the full placement-and-routing state as JSON.
+        pub fn to_json_writer<W: Write>(&self, writer: W) -> anyhow::Result<()> {
+            serde_json::to_writer(writer, self)?;
+            Ok(())
+        }
+
+        /// Reads back a placement-and-routing state previously written by
+        /// `to_json_writer`.
+        pub fn from_json_reader<R: Read>(reader: R) -> anyhow::Result<Self> {
+            Ok(serde_json::from_reader(reader)?)
+        }
+    }
+}
@@ -1,11 +1,13 @@
-use crate::utilities::UnionFind;
-use anyhow::{Error, Result};
+use crate::ids::{BlockageId, CellId, LayerId, MasterCellId, NetId, PinId};
+use crate::utilities::{BitMatrix, UnionFind};
+use anyhow::{anyhow, Error, Result};
 use arrayvec::ArrayVec;
 use num::Num;
 use std::{
     cmp,
-    collections::{HashMap, HashSet},
-    fmt::{Display, Error as FmtError, Formatter, Result as FmtResult},
+    collections::{hash_map::DefaultHasher, hash_map::Entry, HashMap, HashSet},
+    fmt::{Display, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
     str::FromStr,
     usize,
 };
@@ -39,8 +41,41 @@ pub trait FactoryID {
     }
 }
 
+/// Serde helpers that render an id via its `FactoryID` prefixed name (e.g.
+/// `"C7"`) instead of a bare number. The derives on `Cell`/`Net` id fields
+/// opt into this form directly with `#[serde(with = "named_id")]`; `T` is
+/// inferred from the field's concrete id newtype (`CellId`, `NetId`, ...),
+/// which already implements `FactoryID` and the `usize` conversions.
+#[cfg(feature = "serde")]
+pub mod named_id {
+    use super::FactoryID;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T, S>(id: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: FactoryID + Copy + Into<usize>,
+        S: Serializer,
+    {
+        T::from_numeric((*id).into())
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FactoryID + From<usize>,
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        T::from_str(&name)
+            .map_err(serde::de::Error::custom)
+            .map(T::from)
+    }
+}
+
 /// Directions of a layer
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Direction {
     Horizontal,
     Vertical,
@@ -55,6 +90,7 @@ pub enum ConflictType {
 
 /// Whether a cell is movable
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CellType {
     Movable,
     Fixed,
@@ -73,61 +109,70 @@ pub enum Towards {
 
 /// A 2-dimension tuple representing a Pair.
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pair<T>(pub T, pub T)
 where
     T: Copy + Num;
 
 /// A 3-dimension tuple representing a Point.
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point<T>(pub T, pub T, pub T)
 where
     T: Copy + Num;
 
 /// A source point and a target point representing a Route.
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Route<T>(pub Point<T>, pub Point<T>)
 where
     T: Copy + Num;
 
 /// Some information about a Layer.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Layer {
     /// layer id (starts from 0)
-    pub id: usize,
+    pub id: LayerId,
     /// horizontal or vertical
     pub direction: Direction,
     /// dimensions
     pub dim: Pair<usize>,
     /// all grids' capacity
     pub capacity: Vec<usize>,
+    /// one bit per grid, set once its accumulated demand exceeds capacity
+    pub overflow: BitMatrix,
 }
 
 /// Some information about a MasterPin.
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MasterPin {
     /// id of the pin
-    pub id: usize,
+    pub id: PinId,
     /// layer on which the pin is on
-    pub layer: usize,
+    pub layer: LayerId,
 }
 
 /// Some information about a Blockage.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Blockage {
     /// id of the blockage
-    pub id: usize,
+    pub id: BlockageId,
     /// layer on which the blockage is on
-    pub layer: usize,
+    pub layer: LayerId,
     /// extra demand the blockage will cost
     pub demand: usize,
 }
 
 /// Some information about a MasterCell.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MasterCell {
     /// id of cell
-    pub id: usize,
+    pub id: MasterCellId,
     /// number of pins
     pub pins: HashSet<MasterPin>,
     /// number of blockages
@@ -141,30 +186,35 @@ pub struct Conflict {
     /// adjHGGrid or sameGGrid
     pub kind: ConflictType,
     /// other id
-    pub id: usize,
+    pub id: MasterCellId,
     /// on which layer
-    pub layer: usize,
+    pub layer: LayerId,
     /// by how much
     pub demand: usize,
 }
 
 /// Some information about a Cell
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cell {
-    /// id of the cell
-    pub id: usize,
+    /// id of the cell, serialized as its prefixed name (e.g. `"C7"`) rather
+    /// than a bare number; see `named_id`.
+    #[cfg_attr(feature = "serde", serde(with = "named_id"))]
+    pub id: CellId,
     /// if the cell can be moved
     pub movable: CellType,
     /// position
     pub position: Pair<usize>,
     /// mastercell type
-    pub pins: Vec<usize>,
+    pub pins: Vec<PinId>,
 }
 
 /// Pointer points to the nearby node.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pointer {
-    /// nearby node index
+    /// nearby node index, a position in `NetTree::nodes` rather than any
+    /// `FactoryID` id space
     index: usize,
     /// nearby node height
     height: usize,
@@ -172,9 +222,10 @@ pub struct Pointer {
 
 /// A node in a tree.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NetNode {
     /// corresponding to pin id, None represents a virtual node.
-    pub id: Option<usize>,
+    pub id: Option<PinId>,
     /// positions
     pub position: Pair<usize>,
     /// nearby nodes
@@ -189,16 +240,62 @@ pub struct NetNode {
 
 /// Net represented as a tree.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NetTree {
     /// All nodes in a tree
     nodes: Vec<NetNode>,
+    /// Fingerprint contribution of the original `Route` segments (including
+    /// their `lay()` endpoints), folded in once at construction time since
+    /// via/layer-change segments (`Towards::Top`/`Bottom`) never make it
+    /// into `nodes`/`Pointer` otherwise and would be invisible to
+    /// `fingerprint` without it.
+    segment_fingerprint: Fingerprint,
+}
+
+/// A stable 128-bit hash of a net's topology, used to detect whether a net
+/// needs to be re-routed after cells move.
+///
+/// Equal fingerprints imply identical node positions and segment sets,
+/// regardless of the order those were discovered in; any coordinate or
+/// connectivity change flips the result.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fingerprint(pub u64, pub u64);
+
+impl Fingerprint {
+    // Arbitrary fixed seeds so the two lanes don't just mirror each other.
+    const SEED_A: u64 = 0x9E37_79B9_7F4A_7C15;
+    const SEED_B: u64 = 0xC2B2_AE3D_27D4_EB4F;
+
+    fn hash_seeded<T: Hash>(seed: u64, value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn of<T: Hash>(value: &T) -> Self {
+        Fingerprint(
+            Self::hash_seeded(Self::SEED_A, value),
+            Self::hash_seeded(Self::SEED_B, value),
+        )
+    }
+
+    /// Combines two fingerprints with a commutative reducer, so that
+    /// iteration order over the hashed elements doesn't affect the result.
+    fn combine(self, other: Self) -> Self {
+        Fingerprint(self.0 ^ other.0, self.1.wrapping_add(other.1))
+    }
 }
 
 /// Some information about a Net.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Net {
-    /// id of the net
-    pub id: usize,
+    /// id of the net, serialized as its prefixed name (e.g. `"N3"`) rather
+    /// than a bare number; see `named_id`.
+    #[cfg_attr(feature = "serde", serde(with = "named_id"))]
+    pub id: NetId,
     /// min layer id
     pub min_layer: usize,
     /// Structure of the net represented as a tree
@@ -271,35 +368,16 @@ impl Layer {
     pub fn get_capacity_mut(&mut self, row: usize, col: usize) -> Option<&mut usize> {
         self.capacity.get_mut(row * self.dim.y() + col)
     }
-}
-
-impl FactoryID for Layer {
-    fn prefix() -> &'static str {
-        "M"
-    }
-}
-
-impl FactoryID for MasterPin {
-    fn prefix() -> &'static str {
-        "P"
-    }
-}
 
-impl FactoryID for Blockage {
-    fn prefix() -> &'static str {
-        "B"
+    /// Records that `(row, col)`'s accumulated demand has exceeded its
+    /// capacity.
+    pub fn mark_overflow(&mut self, row: usize, col: usize) {
+        self.overflow.set(row, col);
     }
-}
 
-impl FactoryID for MasterCell {
-    fn prefix() -> &'static str {
-        "MC"
-    }
-}
-
-impl FactoryID for Cell {
-    fn prefix() -> &'static str {
-        "C"
+    /// O(1) check for whether `(row, col)` is over-demand.
+    pub fn is_overflowed(&self, row: usize, col: usize) -> bool {
+        self.overflow.contains(row, col)
     }
 }
 
@@ -397,67 +475,101 @@ impl NetNode {
             Towards::Top | Towards::Bottom => unreachable!(),
         }
     }
+
+    /// Hashes this node's position together with its four neighbor heights.
+    fn fingerprint(&self) -> Fingerprint {
+        let heights: [Option<usize>; 4] = self
+            .neightbors()
+            .map(|ptr| ptr.map(|pointer| pointer.height));
+
+        Fingerprint::of(&(self.position, heights))
+    }
 }
 
 impl NetTree {
-    pub fn new<F>(conn_pins: Vec<usize>, segments: HashSet<Route<usize>>, pin_position: F) -> Self
+    pub fn new<F>(conn_pins: Vec<PinId>, segments: HashSet<Route<usize>>, pin_position: F) -> Self
     where
-        F: Fn(usize) -> Option<Pair<usize>>,
+        F: Fn(PinId) -> Option<Pair<usize>>,
     {
-        // Using handcrafted `fold` first instead of direct using `collect` here
-        // to bypass implementation details of `collect`
-        let mut nodes: Vec<NetNode> = segments
-            .iter()
-            .map(|&Route(source, target)| [source, target])
-            .map(ArrayVec::from)
-            .map(ArrayVec::into_iter)
-            .flatten()
-            .map(|ref pt| pt.flatten())
-            .map(|pin| (pin, None))
-            .chain(conn_pins.into_iter().map(|idx| {
-                (
-                    pin_position(idx).expect("Pin not found in database"),
-                    Some(idx),
-                )
-            }))
-            .fold(HashMap::new(), |mut hmap, (position, idx)| {
-                *hmap.entry(position).or_insert(Option::default()) = idx;
-                hmap
-            })
-            .into_iter()
-            .map(|(position, id)| NetNode {
-                id,
-                position,
-                up: None,
-                down: None,
-                left: None,
-                right: None,
-            })
-            .collect();
+        Self::try_new(conn_pins, segments, pin_position).expect("failed to build NetTree")
+    }
+
+    /// Fallible variant of `new` for very large designs: checks every
+    /// internal allocation with `try_reserve`/`try_reserve_exact` and turns
+    /// the missing-pin / out-of-bounds / disconnected-segment panics into
+    /// `Err`s, so a server routing many chips can degrade gracefully under
+    /// memory pressure instead of aborting.
+    pub fn try_new<F>(
+        conn_pins: Vec<PinId>,
+        segments: HashSet<Route<usize>>,
+        pin_position: F,
+    ) -> Result<Self>
+    where
+        F: Fn(PinId) -> Option<Pair<usize>>,
+    {
+        // Using handcrafted loops first instead of direct `collect` here
+        // to bypass implementation details of `collect` and check
+        // allocations along the way.
+        let mut positions: HashMap<Pair<usize>, Option<PinId>> = HashMap::new();
+        positions.try_reserve(segments.len() * 2 + conn_pins.len())?;
+
+        for &Route(source, target) in segments.iter() {
+            for pt in ArrayVec::from([source, target]) {
+                positions.entry(pt.flatten()).or_insert(None);
+            }
+        }
+
+        for idx in conn_pins {
+            let position =
+                pin_position(idx).ok_or_else(|| anyhow!("pin {} not found in database", idx))?;
+
+            match positions.entry(position) {
+                Entry::Occupied(entry) if entry.get().is_some() => {
+                    return Err(anyhow!(
+                        "duplicate node position {:?} claimed by pin {}",
+                        position,
+                        idx
+                    ));
+                }
+                Entry::Occupied(mut entry) => *entry.get_mut() = Some(idx),
+                Entry::Vacant(entry) => {
+                    entry.insert(Some(idx));
+                }
+            }
+        }
+
+        let mut nodes: Vec<NetNode> = Vec::new();
+        nodes.try_reserve_exact(positions.len())?;
+        nodes.extend(positions.into_iter().map(|(position, id)| NetNode {
+            id,
+            position,
+            up: None,
+            down: None,
+            left: None,
+            right: None,
+        }));
 
         let num_nodes = nodes.len();
 
-        let position_to_idx: HashMap<Pair<usize>, usize> = nodes
-            .iter()
-            .enumerate()
-            .map(|(idx, node)| (node.position, idx))
-            .collect();
-
-        debug_assert_eq!(
-            nodes
-                .iter()
-                .map(|node| { node.position })
-                .collect::<HashSet<_>>()
-                .len(),
-            num_nodes
-        );
+        let mut position_to_idx: HashMap<Pair<usize>, usize> = HashMap::new();
+        position_to_idx.try_reserve(num_nodes)?;
+        position_to_idx.extend(nodes.iter().enumerate().map(|(idx, node)| (node.position, idx)));
 
         debug_assert_eq!(position_to_idx.len(), num_nodes);
 
-        let mut union_find = UnionFind::new(num_nodes);
+        let mut union_find = UnionFind::try_new(num_nodes)?;
 
         let mut uf_cnt = 0;
 
+        // Folded in before the consuming loop below: every segment,
+        // including the via/layer-change ones filtered out of the node
+        // graph, contributes its full (row, col, lay) endpoints so that a
+        // via-only reroute still flips the tree's fingerprint.
+        let segment_fingerprint = segments
+            .iter()
+            .map(Fingerprint::of)
+            .fold(Fingerprint::default(), Fingerprint::combine);
+
         for route in segments.into_iter().filter(|elem| match elem.towards() {
             Towards::Up | Towards::Down | Towards::Left | Towards::Right => true,
             Towards::Top | Towards::Bottom => false,
@@ -477,24 +589,53 @@ impl NetTree {
 
             let source_idx = *position_to_idx
                 .get(&source_pos)
-                .expect("Index out of bounds");
+                .ok_or_else(|| anyhow!("segment endpoint {:?} has no matching node", source_pos))?;
             let target_idx = *position_to_idx
                 .get(&target_pos)
-                .expect("Index out of bounds");
+                .ok_or_else(|| anyhow!("segment endpoint {:?} has no matching node", target_pos))?;
 
             if !union_find.union(source_idx, target_idx) {
-                debug_assert_eq!(uf_cnt, 0);
                 uf_cnt += 1;
+                if uf_cnt > 1 {
+                    return Err(anyhow!(
+                        "more than one union-find merge was skipped; net topology is not a tree"
+                    ));
+                }
                 continue;
             }
 
             Self::connect(&mut nodes, source_idx, target_idx, height, towards);
         }
 
-        debug_assert!(union_find.done());
-        debug_assert_eq!(uf_cnt, 1);
+        if !union_find.done() {
+            return Err(anyhow!(
+                "net is disconnected: segments do not link every node"
+            ));
+        }
+        debug_assert!(uf_cnt <= 1);
+
+        Ok(Self {
+            nodes,
+            segment_fingerprint,
+        })
+    }
 
-        Self { nodes }
+    /// Crate-visible view of the tree's nodes, used by `Chip` to walk a
+    /// net's pins without exposing the backing storage.
+    pub(crate) fn nodes(&self) -> &[NetNode] {
+        &self.nodes
+    }
+
+    /// Order-independent, position-stable topology fingerprint: combining
+    /// per-node hashes and the segment fingerprint with a commutative
+    /// reducer means the result doesn't depend on `HashSet`/`HashMap`
+    /// iteration order in `NetTree::new`, while still reflecting every
+    /// segment endpoint (including vias dropped from the node graph).
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.nodes
+            .iter()
+            .map(NetNode::fingerprint)
+            .fold(self.segment_fingerprint, Fingerprint::combine)
     }
 
     /// Connects two different nodes.
@@ -524,21 +665,41 @@ impl NetTree {
 
 impl Net {
     pub fn new<F>(
-        id: usize,
+        id: NetId,
         min_layer: usize,
-        conn_pins: Vec<usize>,
+        conn_pins: Vec<PinId>,
         segments: HashSet<Route<usize>>,
         pin_position: F,
     ) -> Self
     where
-        F: Fn(usize) -> Option<Pair<usize>>,
+        F: Fn(PinId) -> Option<Pair<usize>>,
     {
-        let tree = NetTree::new(conn_pins, segments, pin_position);
-        Self {
+        Self::try_new(id, min_layer, conn_pins, segments, pin_position)
+            .expect("failed to build Net")
+    }
+
+    /// Fallible variant of `new`; see `NetTree::try_new`.
+    pub fn try_new<F>(
+        id: NetId,
+        min_layer: usize,
+        conn_pins: Vec<PinId>,
+        segments: HashSet<Route<usize>>,
+        pin_position: F,
+    ) -> Result<Self>
+    where
+        F: Fn(PinId) -> Option<Pair<usize>>,
+    {
+        let tree = NetTree::try_new(conn_pins, segments, pin_position)?;
+        Ok(Self {
             id,
             min_layer,
             tree,
-        }
+        })
+    }
+
+    /// Topology fingerprint of this net's tree; see `NetTree::fingerprint`.
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.tree.fingerprint()
     }
 
     fn fmt_recursive(
@@ -576,7 +737,7 @@ impl Net {
 
 impl Display for Net {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        let name = &Self::from_numeric(self.id).map_err(|_| FmtError)?;
+        let name = &self.id.to_string();
 
         for node in self.tree.nodes.iter() {
             let Pair(row, col) = node.position;
@@ -598,8 +759,58 @@ impl Display for Net {
     }
 }
 
-impl FactoryID for Net {
-    fn prefix() -> &'static str {
-        "N"
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two pins at (0,0) and (0,1) on layer 0, joined by a single
+    /// horizontal segment. The segment is stored both forward and backward
+    /// so the tree-building loop sees one redundant union, the most
+    /// `NetTree::try_new` accepts for a connected net.
+    fn two_pin_segments() -> HashSet<Route<usize>> {
+        let a = Point(0, 0, 0);
+        let b = Point(0, 1, 0);
+        HashSet::from([Route(a, b), Route(b, a)])
+    }
+
+    fn two_pin_position(id: PinId) -> Option<Pair<usize>> {
+        match usize::from(id) {
+            0 => Some(Pair(0, 0)),
+            1 => Some(Pair(0, 1)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn fingerprint_is_order_independent() {
+        let conn_pins = vec![PinId::from(0), PinId::from(1)];
+
+        let forward = NetTree::try_new(conn_pins.clone(), two_pin_segments(), two_pin_position)
+            .expect("valid tree");
+        let reversed = NetTree::try_new(
+            conn_pins.into_iter().rev().collect(),
+            two_pin_segments(),
+            two_pin_position,
+        )
+        .expect("valid tree");
+
+        assert_eq!(forward.fingerprint(), reversed.fingerprint());
+    }
+
+    #[test]
+    fn via_only_reroute_flips_fingerprint() {
+        let conn_pins = vec![PinId::from(0), PinId::from(1)];
+
+        let planar_only =
+            NetTree::try_new(conn_pins.clone(), two_pin_segments(), two_pin_position)
+                .expect("valid tree");
+
+        let mut with_via = two_pin_segments();
+        with_via.insert(Route(Point(0, 0, 0), Point(0, 0, 1)));
+
+        let rerouted = NetTree::try_new(conn_pins, with_via, two_pin_position).expect("valid tree");
+
+        assert_ne!(planar_only.fingerprint(), rerouted.fingerprint());
     }
 }
+
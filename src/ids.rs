@@ -0,0 +1,74 @@
+use crate::FactoryID;
+use std::fmt::{self, Display, Formatter};
+
+/// Declares a `#[repr(transparent)]` newtype over `usize` tied to a
+/// `FactoryID` prefix, so ids from different spaces (a `Cell` vs. a
+/// `MasterPin`, say) can't be passed to each other by mistake. The `±1`
+/// offset arithmetic in `FactoryID::from_str`/`from_numeric` is applied
+/// only where an id is rendered or parsed as a prefixed name (these
+/// wrappers' `Display` impl and the `named_id` serde helpers); the
+/// wrapped value is always the flat numeric id.
+macro_rules! id_type {
+    ($(#[$meta:meta])* $name:ident, $prefix:literal) => {
+        $(#[$meta])*
+        #[repr(transparent)]
+        #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub struct $name(usize);
+
+        impl From<usize> for $name {
+            fn from(raw: usize) -> Self {
+                Self(raw)
+            }
+        }
+
+        impl From<$name> for usize {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl FactoryID for $name {
+            fn prefix() -> &'static str {
+                $prefix
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+                write!(f, "{}", Self::from_numeric(self.0).map_err(|_| fmt::Error)?)
+            }
+        }
+    };
+}
+
+id_type!(
+    /// Identifies a `Layer`.
+    LayerId,
+    "M"
+);
+id_type!(
+    /// Identifies a `MasterPin`.
+    PinId,
+    "P"
+);
+id_type!(
+    /// Identifies a `Blockage`.
+    BlockageId,
+    "B"
+);
+id_type!(
+    /// Identifies a `MasterCell`.
+    MasterCellId,
+    "MC"
+);
+id_type!(
+    /// Identifies a `Cell`.
+    CellId,
+    "C"
+);
+id_type!(
+    /// Identifies a `Net`.
+    NetId,
+    "N"
+);
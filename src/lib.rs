@@ -2,9 +2,11 @@ mod args;
 mod chip;
 mod components;
 mod consts;
+mod ids;
 mod utilities;
 
 pub use args::Args;
 pub use chip::Chip;
 pub use components::*;
-pub use utilities::UnionFind;
+pub use ids::{BlockageId, CellId, LayerId, MasterCellId, NetId, PinId};
+pub use utilities::{BitMatrix, UnionFind};
@@ -0,0 +1,223 @@
+use anyhow::Result;
+use std::cmp::Ordering;
+
+/// Disjoint-set forest with path compression and union by rank.
+#[derive(Debug)]
+pub struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<usize>,
+    groups: usize,
+}
+
+impl UnionFind {
+    pub fn new(len: usize) -> Self {
+        Self::try_new(len).expect("failed to allocate UnionFind buffers")
+    }
+
+    /// Like `new`, but checks the `parent`/`rank` allocations instead of
+    /// aborting the process on allocation failure.
+    pub fn try_new(len: usize) -> Result<Self> {
+        let mut parent = Vec::new();
+        parent.try_reserve_exact(len)?;
+        parent.extend(0..len);
+
+        let mut rank = Vec::new();
+        rank.try_reserve_exact(len)?;
+        rank.resize(len, 0);
+
+        Ok(Self {
+            parent,
+            rank,
+            groups: len,
+        })
+    }
+
+    /// Finds the representative of `idx`'s set, compressing the path along the way.
+    pub fn find(&mut self, idx: usize) -> usize {
+        if self.parent[idx] != idx {
+            self.parent[idx] = self.find(self.parent[idx]);
+        }
+        self.parent[idx]
+    }
+
+    /// Unions the sets containing `a` and `b`. Returns whether they were disjoint.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return false;
+        }
+
+        match self.rank[ra].cmp(&self.rank[rb]) {
+            Ordering::Less => self.parent[ra] = rb,
+            Ordering::Greater => self.parent[rb] = ra,
+            Ordering::Equal => {
+                self.parent[rb] = ra;
+                self.rank[ra] += 1;
+            }
+        }
+
+        self.groups -= 1;
+        true
+    }
+
+    /// Whether every element has been merged into a single set.
+    pub fn done(&self) -> bool {
+        self.groups == 1
+    }
+}
+
+/// Bit-packed row-major boolean matrix, e.g. for grid-occupancy or
+/// conflict-adjacency queries that would otherwise need a `HashSet`/`Vec`
+/// scan.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BitMatrix {
+    words: Vec<u64>,
+    rows: usize,
+    cols: usize,
+    words_per_row: usize,
+}
+
+impl BitMatrix {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Self::try_new(rows, cols).expect("failed to allocate BitMatrix")
+    }
+
+    /// Like `new`, but checks the backing allocation instead of aborting
+    /// the process on allocation failure.
+    pub fn try_new(rows: usize, cols: usize) -> Result<Self> {
+        let words_per_row = cols.div_ceil(64);
+        let mut words = Vec::new();
+        words.try_reserve_exact(rows * words_per_row)?;
+        words.resize(rows * words_per_row, 0u64);
+
+        Ok(Self {
+            words,
+            rows,
+            cols,
+            words_per_row,
+        })
+    }
+
+    fn word_index(&self, row: usize, col: usize) -> (usize, u32) {
+        debug_assert!(row < self.rows && col < self.cols);
+        (row * self.words_per_row + col / 64, (col % 64) as u32)
+    }
+
+    /// Sets the bit at `(row, col)`.
+    pub fn set(&mut self, row: usize, col: usize) {
+        let (word, bit) = self.word_index(row, col);
+        self.words[word] |= 1u64 << bit;
+    }
+
+    /// Whether the bit at `(row, col)` is set.
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        let (word, bit) = self.word_index(row, col);
+        (self.words[word] >> bit) & 1 != 0
+    }
+
+    /// ORs `src_row`'s words into `dst_row`. Returns whether any bit changed.
+    pub fn union_into(&mut self, dst_row: usize, src_row: usize) -> bool {
+        debug_assert!(dst_row < self.rows && src_row < self.rows);
+
+        let mut changed = false;
+
+        for word in 0..self.words_per_row {
+            let src = self.words[src_row * self.words_per_row + word];
+            let dst_idx = dst_row * self.words_per_row + word;
+            let merged = self.words[dst_idx] | src;
+
+            if merged != self.words[dst_idx] {
+                self.words[dst_idx] = merged;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// Iterates the set bits of `row` in ascending column order.
+    pub fn iter_row(&self, row: usize) -> BitVectorIter<'_> {
+        debug_assert!(row < self.rows);
+        let start = row * self.words_per_row;
+        BitVectorIter {
+            words: &self.words[start..start + self.words_per_row],
+            word_idx: 0,
+            current: 0,
+        }
+    }
+}
+
+/// Iterator over the set bit positions of one `BitMatrix` row.
+pub struct BitVectorIter<'a> {
+    words: &'a [u64],
+    word_idx: usize,
+    current: u64,
+}
+
+impl<'a> Iterator for BitVectorIter<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            if self.word_idx >= self.words.len() {
+                return None;
+            }
+            self.current = self.words[self.word_idx];
+            self.word_idx += 1;
+        }
+
+        let bit = self.current.trailing_zeros() as usize;
+        self.current &= self.current - 1;
+        Some((self.word_idx - 1) * 64 + bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_contains_and_iter_row() {
+        let mut matrix = BitMatrix::new(3, 10);
+        matrix.set(1, 0);
+        matrix.set(1, 9);
+        matrix.set(1, 4);
+
+        assert!(matrix.contains(1, 0));
+        assert!(matrix.contains(1, 4));
+        assert!(matrix.contains(1, 9));
+        assert!(!matrix.contains(1, 1));
+        assert!(!matrix.contains(0, 0));
+
+        assert_eq!(matrix.iter_row(1).collect::<Vec<_>>(), vec![0, 4, 9]);
+        assert_eq!(matrix.iter_row(0).collect::<Vec<_>>(), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn exact_multiple_of_64_columns_has_no_spare_word() {
+        let mut matrix = BitMatrix::new(2, 64);
+        matrix.set(0, 63);
+        matrix.set(1, 0);
+
+        assert_eq!(matrix.words_per_row, 1);
+        assert!(matrix.contains(0, 63));
+        assert!(!matrix.contains(0, 0));
+        assert_eq!(matrix.iter_row(0).collect::<Vec<_>>(), vec![63]);
+        assert_eq!(matrix.iter_row(1).collect::<Vec<_>>(), vec![0]);
+    }
+
+    #[test]
+    fn union_into_merges_rows_and_reports_change() {
+        let mut matrix = BitMatrix::new(2, 128);
+        matrix.set(0, 3);
+        matrix.set(1, 3);
+        matrix.set(1, 100);
+
+        assert!(matrix.union_into(0, 1));
+        assert_eq!(matrix.iter_row(0).collect::<Vec<_>>(), vec![3, 100]);
+
+        // Merging again adds nothing new.
+        assert!(!matrix.union_into(0, 1));
+    }
+}